@@ -7,7 +7,8 @@ use std::fs;
 use std::path::PathBuf;
 use std::sync::Arc;
 use env_logger::Env;
-use vectordb::vectorstore::{VectorDB, ShardDB, DistanceMetric};
+use vectordb::vectorstore::{VectorDB, ShardDB, DistanceMetric, SearchMode};
+use vectordb::quantization::QuantizationMode;
 
 mod vectordb;
 
@@ -25,6 +26,11 @@ pub struct CreateCollectionRequest {
     name: String,
     metric: String,
     dimension: usize,
+    /// `none` (default), `scalar_int8`, or `product_quantization`.
+    quantization: Option<String>,
+    /// Number of independent HNSW shards to split the collection across;
+    /// defaults to `num_cpus::get()` when omitted.
+    shards: Option<usize>,
 }
 
 #[derive(Deserialize, Clone)]
@@ -41,11 +47,26 @@ struct AddDocumentsRequest {
     documents: Vec<AddDocumentRequest>,
 }
 
+#[derive(Deserialize)]
+struct UpdateDocumentRequest {
+    embedding: Vec<f64>,
+    metadata: String,
+    content: String,
+}
+
 #[derive(Deserialize)]
 struct SearchRequest {
+    #[serde(default)]
     query: Vec<f64>,
-    n: usize, 
+    n: usize,
     collection_name: String,
+    /// Optional metadata filter DSL, e.g. `{"AND": [{"genre": "scifi"}, {"year": {">=": 2000}}]}`.
+    filter: Option<serde_json::Value>,
+    /// Query text for `keyword`/`hybrid` search modes.
+    text: Option<String>,
+    /// `vector` (default), `keyword`, or `hybrid`.
+    #[serde(default)]
+    mode: SearchMode,
 }
 
 #[derive(Serialize)]
@@ -54,7 +75,9 @@ struct CollectionResponse {
     name: String,
     metric: DistanceMetric,
     dimension: usize,
-    doc_count: u64
+    doc_count: u64,
+    quantization: QuantizationMode,
+    shard_count: usize,
 }
 
 #[derive(Serialize)]
@@ -88,6 +111,11 @@ struct StatsResponse {
     collections: usize,
     total_documents: usize,
     memory_usage: std::collections::HashMap<String, usize>,
+    /// `raw_vector_bytes / quantized_vector_bytes` across all collections;
+    /// 1.0 when nothing is quantized yet. This reflects RocksDB's persisted
+    /// storage only — HNSW always indexes full-precision vectors, so it is
+    /// not a process-memory savings figure.
+    compression_ratio: f64,
 }
 
 // Health Check
@@ -112,14 +140,21 @@ async fn create_collection(
 ) -> impl Responder {
     let metric = DistanceMetric::from_str(&req.metric)
         .unwrap_or(DistanceMetric::Cosine);
+    let quantization = req
+        .quantization
+        .as_deref()
+        .and_then(QuantizationMode::from_str)
+        .unwrap_or_default();
 
-    match db.create_collection(&req.name, metric.clone(), req.dimension) {
+    match db.create_collection(&req.name, metric.clone(), req.dimension, quantization, req.shards) {
         Ok(meta) => HttpResponse::Ok().json(CollectionResponse {
             id: meta.id,
             name: meta.name,
             metric,
             dimension: meta.dim,
-            doc_count: meta.doc_count
+            doc_count: meta.doc_count,
+            quantization: meta.quantization,
+            shard_count: meta.shard_count,
         }),
         Err(e) if e == "duplicate" => HttpResponse::Conflict().json(json!({
             "error": "Collection already exists",
@@ -141,7 +176,9 @@ async fn list_collections(db: web::Data<ShardDB<'static>>) -> impl Responder {
             name: m.name,
             metric: m.metric,
             dimension: m.dim,
-            doc_count: m.doc_count
+            doc_count: m.doc_count,
+            quantization: m.quantization,
+            shard_count: m.shard_count,
         })
         .collect();
     HttpResponse::Ok().json(resp)
@@ -157,7 +194,9 @@ async fn get_collection_by_name(
             name: m.name,
             metric: m.metric,
             dimension: m.dim,
-            doc_count: m.doc_count
+            doc_count: m.doc_count,
+            quantization: m.quantization,
+            shard_count: m.shard_count,
         }),
         Err(_) => HttpResponse::NotFound().json(json!({
             "error": "Collection not found",
@@ -219,12 +258,49 @@ async fn add_documents(
     }
 }
 
+async fn delete_document(
+    db: web::Data<ShardDB<'static>>,
+    path: web::Path<(String, i32)>,
+) -> impl Responder {
+    let (collection_name, id) = path.into_inner();
+    match db.delete_document(&collection_name, id as u64) {
+        Ok(()) => HttpResponse::Ok().json(json!({ "status": "deleted", "id": id })),
+        Err(e) => HttpResponse::NotFound().json(json!({
+            "error": e,
+            "collection_name": collection_name
+        })),
+    }
+}
+
+async fn update_document(
+    db: web::Data<ShardDB<'static>>,
+    path: web::Path<(String, i32)>,
+    item: web::Json<UpdateDocumentRequest>,
+) -> impl Responder {
+    let (collection_name, id) = path.into_inner();
+    let emb: Vec<f32> = item.embedding.iter().map(|v| *v as f32).collect();
+    match db.update_document(&collection_name, id as u64, emb, item.metadata.clone(), item.content.clone()) {
+        Ok(()) => HttpResponse::Ok().json(json!({ "status": "updated", "id": id })),
+        Err(e) => HttpResponse::BadRequest().json(json!({
+            "error": e,
+            "collection_name": collection_name
+        })),
+    }
+}
+
 async fn retrieve_documents(
     db: web::Data<ShardDB<'static>>,
     req: web::Json<SearchRequest>,
 ) -> impl Responder {
     let query: Vec<f32> = req.query.iter().map(|v| *v as f32).collect();
-    match db.search(&req.collection_name, &query, req.n) {
+    match db.search(
+        &req.collection_name,
+        &query,
+        req.n,
+        req.filter.as_ref(),
+        req.mode,
+        req.text.as_deref(),
+    ) {
         Ok(hits) => {
             let resp: Vec<NearestNeighbor> = hits
                 .into_iter()
@@ -244,16 +320,40 @@ async fn retrieve_documents(
     }
 }
 
+async fn flush_collection(
+    db: web::Data<ShardDB<'static>>,
+    path: web::Path<String>,
+) -> impl Responder {
+    match db.flush(&path) {
+        Ok(()) => HttpResponse::Ok().json(json!({
+            "status": "flushed",
+            "collection_name": path.as_str()
+        })),
+        Err(e) => HttpResponse::NotFound().json(json!({
+            "error": e,
+            "collection_name": path.as_str()
+        })),
+    }
+}
+
 async fn get_stats(db: web::Data<ShardDB<'static>>) -> impl Responder {
     let collections = db.list_collections();
     let total_docs: u64 = collections.iter().map(|c| c.doc_count).sum();
     
     let memory_stats = db.get_memory_stats();
-    
+    let raw_bytes = *memory_stats.get("raw_vector_bytes").unwrap_or(&0);
+    let quantized_bytes = *memory_stats.get("quantized_vector_bytes").unwrap_or(&0);
+    let compression_ratio = if quantized_bytes > 0 {
+        raw_bytes as f64 / quantized_bytes as f64
+    } else {
+        1.0
+    };
+
     HttpResponse::Ok().json(StatsResponse {
         collections: collections.len(),
         total_documents: total_docs as usize,
         memory_usage: memory_stats,
+        compression_ratio,
     })
 }
 
@@ -289,6 +389,8 @@ async fn main() -> std::io::Result<()> {
     println!("⬢ Workers: {}", workers);
     println!("⬢ Database path: {}", db_path.display());
 
+    let shutdown_db = db.clone();
+
     HttpServer::new(move || {
         App::new()
             .wrap(Logger::default())
@@ -302,8 +404,11 @@ async fn main() -> std::io::Result<()> {
                     .route("/collections", web::post().to(create_collection))
                     .route("/collections", web::get().to(list_collections))
                     .route("/collections/{name}", web::get().to(get_collection_by_name))
+                    .route("/collections/{name}/flush", web::post().to(flush_collection))
                     .route("/documents", web::post().to(add_document))
                     .route("/documents/batch", web::post().to(add_documents))
+                    .route("/documents/{collection}/{id}", web::put().to(update_document))
+                    .route("/documents/{collection}/{id}", web::delete().to(delete_document))
                     .route("/search", web::post().to(retrieve_documents))
             )
             // Legacy routes for backward compatibility
@@ -319,5 +424,10 @@ async fn main() -> std::io::Result<()> {
     .workers(workers)
     .bind(("127.0.0.1", 8444))?
     .run()
-    .await
+    .await?;
+
+    // Snapshot every collection's vectors to RocksDB on a clean shutdown.
+    shutdown_db.shutdown();
+
+    Ok(())
 }
\ No newline at end of file
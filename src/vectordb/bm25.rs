@@ -0,0 +1,169 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+const K1: f32 = 1.2;
+const B: f32 = 0.75;
+
+/// Lowercase and split on non-alphanumeric boundaries, matching the
+/// tokenization the index was built with.
+pub fn tokenize(text: &str) -> Vec<String> {
+    text.to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|s| !s.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Per-collection BM25 inverted index over the `content` field: `term ->
+/// (doc_id, term frequency)` postings plus per-document lengths, persisted
+/// under the `bm25:{collection_id}` key so it survives a restart like the
+/// HNSW graph does.
+#[derive(Default, Serialize, Deserialize)]
+pub struct Bm25Index {
+    postings: HashMap<String, Vec<(u64, u32)>>,
+    doc_lengths: HashMap<u64, u32>,
+}
+
+impl Bm25Index {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// (Re)index a document's content, replacing any prior entry for the id.
+    pub fn index_document(&mut self, doc_id: u64, content: &str) {
+        self.remove_document(doc_id);
+
+        let tokens = tokenize(content);
+        self.doc_lengths.insert(doc_id, tokens.len() as u32);
+
+        let mut term_freqs: HashMap<String, u32> = HashMap::new();
+        for token in tokens {
+            *term_freqs.entry(token).or_insert(0) += 1;
+        }
+        for (term, freq) in term_freqs {
+            self.postings.entry(term).or_default().push((doc_id, freq));
+        }
+    }
+
+    pub fn remove_document(&mut self, doc_id: u64) {
+        if self.doc_lengths.remove(&doc_id).is_none() {
+            return;
+        }
+        for postings in self.postings.values_mut() {
+            postings.retain(|(id, _)| *id != doc_id);
+        }
+    }
+
+    /// Rank documents by BM25 score against `query`, highest score first.
+    pub fn search(&self, query: &str, top_k: usize) -> Vec<(u64, f32)> {
+        let n = self.doc_lengths.len();
+        if n == 0 {
+            return Vec::new();
+        }
+        let avgdl = self.doc_lengths.values().map(|&len| len as f64).sum::<f64>() / n as f64;
+
+        let mut scores: HashMap<u64, f32> = HashMap::new();
+        for term in tokenize(query) {
+            let Some(postings) = self.postings.get(&term) else {
+                continue;
+            };
+            let n_t = postings.len();
+            if n_t == 0 {
+                continue;
+            }
+            let idf = (((n as f64 - n_t as f64 + 0.5) / (n_t as f64 + 0.5)) + 1.0).ln() as f32;
+
+            for &(doc_id, tf) in postings {
+                let dl = *self.doc_lengths.get(&doc_id).unwrap_or(&0) as f32;
+                let tf = tf as f32;
+                let denom = tf + K1 * (1.0 - B + B * dl / avgdl as f32);
+                *scores.entry(doc_id).or_insert(0.0) += idf * (tf * (K1 + 1.0)) / denom;
+            }
+        }
+
+        let mut ranked: Vec<(u64, f32)> = scores.into_iter().collect();
+        ranked.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+        ranked.truncate(top_k);
+        ranked
+    }
+}
+
+/// Fuse independently-ranked id lists via Reciprocal Rank Fusion:
+/// `score(d) = sum(1 / (rank_in_list + c))` over the lists `d` appears in,
+/// with rank starting at 1. Returns ids sorted by fused score, highest first.
+pub fn reciprocal_rank_fusion(lists: &[Vec<u64>], c: f32) -> Vec<(u64, f32)> {
+    let mut scores: HashMap<u64, f32> = HashMap::new();
+    for list in lists {
+        for (rank, doc_id) in list.iter().enumerate() {
+            *scores.entry(*doc_id).or_insert(0.0) += 1.0 / (rank as f32 + 1.0 + c);
+        }
+    }
+
+    let mut ranked: Vec<(u64, f32)> = scores.into_iter().collect();
+    ranked.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+    ranked
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tokenize_lowercases_and_splits_on_punctuation() {
+        assert_eq!(tokenize("The Quick-Brown Fox!"), vec!["the", "quick", "brown", "fox"]);
+    }
+
+    #[test]
+    fn search_ranks_more_relevant_documents_first() {
+        let mut index = Bm25Index::new();
+        index.index_document(1, "the quick brown fox jumps over the lazy dog");
+        index.index_document(2, "fox fox fox");
+        index.index_document(3, "completely unrelated text about nothing");
+
+        let results = index.search("fox", 10);
+        let ids: Vec<u64> = results.iter().map(|(id, _)| *id).collect();
+        assert_eq!(ids[0], 2, "doc with higher term frequency should rank first");
+        assert!(!ids.contains(&3), "doc without the query term should be excluded");
+    }
+
+    #[test]
+    fn search_on_empty_index_returns_no_results() {
+        let index = Bm25Index::new();
+        assert!(index.search("anything", 10).is_empty());
+    }
+
+    #[test]
+    fn remove_document_drops_it_from_future_searches() {
+        let mut index = Bm25Index::new();
+        index.index_document(1, "fox");
+        index.index_document(2, "fox");
+        index.remove_document(1);
+
+        let ids: Vec<u64> = index.search("fox", 10).iter().map(|(id, _)| *id).collect();
+        assert_eq!(ids, vec![2]);
+    }
+
+    #[test]
+    fn reindexing_a_doc_id_replaces_its_old_content() {
+        let mut index = Bm25Index::new();
+        index.index_document(1, "fox");
+        index.index_document(1, "dog");
+
+        assert!(index.search("fox", 10).is_empty());
+        assert_eq!(index.search("dog", 10).iter().map(|(id, _)| *id).collect::<Vec<_>>(), vec![1]);
+    }
+
+    #[test]
+    fn reciprocal_rank_fusion_rewards_agreement_across_lists() {
+        let lists = vec![vec![1, 2, 3], vec![2, 1, 4]];
+        let fused = reciprocal_rank_fusion(&lists, 60.0);
+        let ids: Vec<u64> = fused.iter().map(|(id, _)| *id).collect();
+
+        // 1 and 2 each appear near the top of both lists, so one of them
+        // should win; 4 only appears once, near the bottom, so it should
+        // not outrank either.
+        assert!(ids[0] == 1 || ids[0] == 2);
+        assert!(ids.iter().position(|&id| id == 4).unwrap() > ids.iter().position(|&id| id == 1).unwrap());
+    }
+}
@@ -0,0 +1,141 @@
+use std::cmp::Ordering;
+
+use serde_json::Value;
+
+/// Evaluate a small JSON filter DSL against a document's metadata JSON.
+///
+/// Supported shapes:
+/// - `{"field": value}` — equality
+/// - `{"field": {">": v}}`, `{">=": v}`, `{"<": v}`, `{"<=": v}` — range
+/// - `{"field": {"IN": [v, ...]}}` — membership
+/// - `{"AND": [filter, ...]}` / `{"OR": [filter, ...]}` — boolean composition
+///
+/// Multiple keys at the same level (other than `AND`/`OR`) are implicitly
+/// AND-ed together, mirroring how the rest of this field already treats
+/// `metadata` as opaque JSON.
+pub fn evaluate(filter: &Value, metadata: &Value) -> bool {
+    let Some(map) = filter.as_object() else {
+        return false;
+    };
+
+    map.iter().all(|(key, predicate)| match (key.as_str(), predicate.as_array()) {
+        ("AND", Some(clauses)) => clauses.iter().all(|f| evaluate(f, metadata)),
+        ("OR", Some(clauses)) => clauses.iter().any(|f| evaluate(f, metadata)),
+        _ => eval_field(key, predicate, metadata),
+    })
+}
+
+fn eval_field(field: &str, predicate: &Value, metadata: &Value) -> bool {
+    let Some(actual) = metadata.get(field) else {
+        return false;
+    };
+
+    match predicate {
+        Value::Object(ops) => ops.iter().all(|(op, rhs)| eval_op(op, actual, rhs)),
+        // Compare numbers via `compare()` rather than raw `==`: `Value`'s
+        // `Number` equality doesn't normalize int vs. float representations,
+        // so `json!(2000) == json!(2000.0)` is false even though callers
+        // construct metadata JSON independently of filter JSON and shouldn't
+        // have to match representations to match values.
+        literal @ Value::Number(_) if actual.is_number() => compare(actual, literal) == Some(Ordering::Equal),
+        literal => actual == literal,
+    }
+}
+
+fn eval_op(op: &str, actual: &Value, rhs: &Value) -> bool {
+    match op {
+        ">" => compare(actual, rhs) == Some(Ordering::Greater),
+        ">=" => matches!(compare(actual, rhs), Some(Ordering::Greater) | Some(Ordering::Equal)),
+        "<" => compare(actual, rhs) == Some(Ordering::Less),
+        "<=" => matches!(compare(actual, rhs), Some(Ordering::Less) | Some(Ordering::Equal)),
+        // Same numeric-representation normalization as plain equality in
+        // `eval_field`: a number in the list should match regardless of
+        // whether it or `actual` came through serde_json as an int or float.
+        "IN" => rhs.as_array().is_some_and(|values| {
+            values.iter().any(|v| {
+                if actual.is_number() && v.is_number() {
+                    compare(actual, v) == Some(Ordering::Equal)
+                } else {
+                    v == actual
+                }
+            })
+        }),
+        _ => false,
+    }
+}
+
+fn compare(a: &Value, b: &Value) -> Option<Ordering> {
+    match (a, b) {
+        (Value::Number(a), Value::Number(b)) => a.as_f64()?.partial_cmp(&b.as_f64()?),
+        (Value::String(a), Value::String(b)) => Some(a.cmp(b)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn equality_matches_int_and_float_representations() {
+        let metadata = json!({"year": 2000.0});
+        assert!(evaluate(&json!({"year": 2000}), &metadata));
+
+        let metadata = json!({"year": 2000});
+        assert!(evaluate(&json!({"year": 2000.0}), &metadata));
+    }
+
+    #[test]
+    fn equality_still_rejects_mismatched_strings() {
+        let metadata = json!({"genre": "scifi"});
+        assert!(!evaluate(&json!({"genre": "fantasy"}), &metadata));
+    }
+
+    #[test]
+    fn range_operators() {
+        let metadata = json!({"year": 2010});
+        assert!(evaluate(&json!({"year": {">=": 2000}}), &metadata));
+        assert!(evaluate(&json!({"year": {"<": 2020}}), &metadata));
+        assert!(!evaluate(&json!({"year": {">": 2010}}), &metadata));
+    }
+
+    #[test]
+    fn in_membership() {
+        let metadata = json!({"genre": "scifi"});
+        assert!(evaluate(&json!({"genre": {"IN": ["scifi", "fantasy"]}}), &metadata));
+        assert!(!evaluate(&json!({"genre": {"IN": ["fantasy"]}}), &metadata));
+    }
+
+    #[test]
+    fn in_membership_matches_int_and_float_representations() {
+        let metadata = json!({"year": 2000.0});
+        assert!(evaluate(&json!({"year": {"IN": [1999, 2000]}}), &metadata));
+
+        let metadata = json!({"year": 2000});
+        assert!(evaluate(&json!({"year": {"IN": [2000.0, 2001.0]}}), &metadata));
+    }
+
+    #[test]
+    fn and_or_composition() {
+        let metadata = json!({"genre": "scifi", "year": 2001});
+        assert!(evaluate(
+            &json!({"AND": [{"genre": "scifi"}, {"year": {">=": 2000}}]}),
+            &metadata
+        ));
+        assert!(!evaluate(
+            &json!({"AND": [{"genre": "scifi"}, {"year": {">=": 2020}}]}),
+            &metadata
+        ));
+        assert!(evaluate(
+            &json!({"OR": [{"genre": "fantasy"}, {"year": {">=": 2000}}]}),
+            &metadata
+        ));
+    }
+
+    #[test]
+    fn missing_field_does_not_match() {
+        let metadata = json!({"genre": "scifi"});
+        assert!(!evaluate(&json!({"year": 2000}), &metadata));
+    }
+}
@@ -0,0 +1,4 @@
+pub mod bm25;
+pub mod filter;
+pub mod quantization;
+pub mod vectorstore;
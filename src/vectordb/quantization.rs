@@ -0,0 +1,270 @@
+use serde::{Deserialize, Serialize};
+
+/// How embeddings are compressed before being written to RocksDB's `vec:`
+/// storage. Selected per collection at creation time; `none` keeps today's
+/// full-precision `f32` rkyv payload. The HNSW graph itself always indexes
+/// full-precision `f32` vectors (the original on insert, the decoded vector
+/// on recovery or rebuild) — only the persisted copy shrinks.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum QuantizationMode {
+    #[default]
+    None,
+    ScalarInt8,
+    ProductQuantization,
+}
+
+impl QuantizationMode {
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "none" => Some(Self::None),
+            "scalar_int8" => Some(Self::ScalarInt8),
+            "product_quantization" => Some(Self::ProductQuantization),
+            _ => None,
+        }
+    }
+}
+
+/// Per-dimension min/max scalar quantizer: each `f32` component is mapped to
+/// a `u8`, a quarter of the raw `f32` storage. The range widens as
+/// out-of-range values are observed, so components quantized before a range
+/// widening lose a little precision on the next read — an acceptable
+/// tradeoff at this collection's scale, the same one `bm25`'s full-blob
+/// rewrite and the tombstone rebuild threshold already make elsewhere.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ScalarQuantizer {
+    min: Vec<f32>,
+    max: Vec<f32>,
+    initialized: bool,
+}
+
+impl ScalarQuantizer {
+    pub fn new(dim: usize) -> Self {
+        Self { min: vec![0.0; dim], max: vec![0.0; dim], initialized: false }
+    }
+
+    /// Widen the tracked per-dimension range to cover `vector`.
+    pub fn observe(&mut self, vector: &[f32]) {
+        if !self.initialized {
+            self.min.copy_from_slice(vector);
+            self.max.copy_from_slice(vector);
+            self.initialized = true;
+            return;
+        }
+        for (i, &v) in vector.iter().enumerate() {
+            if v < self.min[i] {
+                self.min[i] = v;
+            }
+            if v > self.max[i] {
+                self.max[i] = v;
+            }
+        }
+    }
+
+    pub fn encode(&self, vector: &[f32]) -> Vec<u8> {
+        vector
+            .iter()
+            .enumerate()
+            .map(|(i, &v)| {
+                let (lo, hi) = (self.min[i], self.max[i]);
+                if hi <= lo {
+                    return 0;
+                }
+                let t = ((v - lo) / (hi - lo)).clamp(0.0, 1.0);
+                (t * 255.0).round() as u8
+            })
+            .collect()
+    }
+
+    pub fn decode(&self, codes: &[u8]) -> Vec<f32> {
+        codes
+            .iter()
+            .enumerate()
+            .map(|(i, &c)| self.min[i] + (c as f32 / 255.0) * (self.max[i] - self.min[i]))
+            .collect()
+    }
+}
+
+/// Number of centroids trained per subspace. `u8` codes cap this at 256.
+pub const PQ_CENTROIDS: usize = 256;
+
+/// Minimum number of raw vectors to collect before training a codebook.
+/// Below this, k-means centroids are too noisy to be worth committing to.
+pub const PQ_TRAIN_MIN_SAMPLES: usize = PQ_CENTROIDS;
+
+const PQ_KMEANS_ITERATIONS: usize = 10;
+
+/// Splits each vector into `m` subvectors and encodes each as the index of
+/// its nearest of `PQ_CENTROIDS` trained centroids, so a `dim`-length `f32`
+/// vector becomes `m` bytes on disk. `decode` reconstructs the full `f32`
+/// vector for HNSW, which always indexes full precision — this only shrinks
+/// the persisted `vecq:` payload, not the in-memory graph.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ProductQuantizer {
+    subvector_dim: usize,
+    m: usize,
+    codebooks: Vec<Vec<Vec<f32>>>,
+}
+
+impl ProductQuantizer {
+    /// Largest subspace count (capped at 8) that evenly divides `dim`.
+    pub fn subspace_count(dim: usize) -> usize {
+        for m in (1..=dim.min(8)).rev() {
+            if dim % m == 0 {
+                return m;
+            }
+        }
+        1
+    }
+
+    /// Train one codebook per subspace via Lloyd's algorithm over `samples`.
+    /// Centroids are seeded by evenly sampling the training set rather than
+    /// randomly, so training is deterministic and doesn't need a `rand`
+    /// dependency this crate otherwise doesn't have.
+    pub fn train(samples: &[Vec<f32>], dim: usize) -> Self {
+        let m = Self::subspace_count(dim);
+        let subvector_dim = dim / m;
+        let k = PQ_CENTROIDS.min(samples.len()).max(1);
+
+        let codebooks = (0..m)
+            .map(|s| {
+                let sub_samples: Vec<&[f32]> = samples
+                    .iter()
+                    .map(|v| &v[s * subvector_dim..(s + 1) * subvector_dim])
+                    .collect();
+                Self::train_subspace(&sub_samples, k)
+            })
+            .collect();
+
+        Self { subvector_dim, m, codebooks }
+    }
+
+    fn train_subspace(samples: &[&[f32]], k: usize) -> Vec<Vec<f32>> {
+        let step = samples.len() / k;
+        let mut centroids: Vec<Vec<f32>> =
+            (0..k).map(|i| samples[(i * step).min(samples.len() - 1)].to_vec()).collect();
+
+        let dim = centroids[0].len();
+        for _ in 0..PQ_KMEANS_ITERATIONS {
+            let mut sums = vec![vec![0.0f32; dim]; k];
+            let mut counts = vec![0usize; k];
+
+            for sample in samples {
+                let nearest = nearest_centroid(sample, &centroids);
+                counts[nearest] += 1;
+                for (d, &v) in sample.iter().enumerate() {
+                    sums[nearest][d] += v;
+                }
+            }
+
+            for c in 0..k {
+                if counts[c] == 0 {
+                    continue;
+                }
+                for d in 0..dim {
+                    centroids[c][d] = sums[c][d] / counts[c] as f32;
+                }
+            }
+        }
+
+        centroids
+    }
+
+    pub fn encode(&self, vector: &[f32]) -> Vec<u8> {
+        (0..self.m)
+            .map(|s| {
+                let sub = &vector[s * self.subvector_dim..(s + 1) * self.subvector_dim];
+                nearest_centroid(sub, &self.codebooks[s]) as u8
+            })
+            .collect()
+    }
+
+    pub fn decode(&self, codes: &[u8]) -> Vec<f32> {
+        let mut out = Vec::with_capacity(self.m * self.subvector_dim);
+        for (s, &code) in codes.iter().enumerate() {
+            out.extend_from_slice(&self.codebooks[s][code as usize]);
+        }
+        out
+    }
+
+    /// Bytes per encoded vector: one byte per subspace.
+    pub fn code_size(&self) -> usize {
+        self.m
+    }
+}
+
+fn nearest_centroid(vector: &[f32], centroids: &[Vec<f32>]) -> usize {
+    centroids
+        .iter()
+        .enumerate()
+        .map(|(i, c)| (i, squared_distance(vector, c)))
+        .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap())
+        .map(|(i, _)| i)
+        .unwrap_or(0)
+}
+
+fn squared_distance(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b).map(|(x, y)| (x - y) * (x - y)).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quantization_mode_from_str_roundtrips() {
+        assert_eq!(QuantizationMode::from_str("none"), Some(QuantizationMode::None));
+        assert_eq!(QuantizationMode::from_str("SCALAR_INT8"), Some(QuantizationMode::ScalarInt8));
+        assert_eq!(QuantizationMode::from_str("product_quantization"), Some(QuantizationMode::ProductQuantization));
+        assert_eq!(QuantizationMode::from_str("bogus"), None);
+    }
+
+    #[test]
+    fn scalar_quantizer_decode_approximates_encode_input() {
+        let mut q = ScalarQuantizer::new(3);
+        q.observe(&[0.0, -5.0, 10.0]);
+        q.observe(&[10.0, 5.0, 20.0]);
+
+        let codes = q.encode(&[5.0, 0.0, 15.0]);
+        let decoded = q.decode(&codes);
+        for (orig, back) in [5.0, 0.0, 15.0].iter().zip(decoded.iter()) {
+            assert!((orig - back).abs() < 0.1, "{orig} vs {back}");
+        }
+    }
+
+    #[test]
+    fn scalar_quantizer_clamps_out_of_range_values() {
+        let mut q = ScalarQuantizer::new(1);
+        q.observe(&[0.0]);
+        q.observe(&[10.0]);
+
+        // A value below the observed range should clamp to the low end
+        // rather than wrapping or panicking.
+        let codes = q.encode(&[-100.0]);
+        assert_eq!(codes, vec![0]);
+    }
+
+    #[test]
+    fn subspace_count_divides_dim_evenly_and_caps_at_eight() {
+        assert_eq!(ProductQuantizer::subspace_count(128), 8);
+        assert_eq!(ProductQuantizer::subspace_count(6), 6);
+        assert_eq!(ProductQuantizer::subspace_count(11), 1); // prime above the cap, only 1 divides evenly
+    }
+
+    #[test]
+    fn product_quantizer_decode_approximates_training_samples() {
+        // Two well-separated clusters so k-means has an obvious answer to
+        // converge to, even with very few centroids.
+        let samples: Vec<Vec<f32>> = (0..16)
+            .map(|i| if i % 2 == 0 { vec![0.0, 0.0, 0.0, 0.0] } else { vec![10.0, 10.0, 10.0, 10.0] })
+            .collect();
+        let pq = ProductQuantizer::train(&samples, 4);
+
+        let codes = pq.encode(&[0.2, 0.1, -0.1, 0.0]);
+        let decoded = pq.decode(&codes);
+        for v in decoded {
+            assert!(v.abs() < 5.0, "expected decode near the low cluster, got {v}");
+        }
+        assert_eq!(pq.code_size(), ProductQuantizer::subspace_count(4));
+    }
+}